@@ -0,0 +1,258 @@
+use crate::bit_set::WordBits;
+use crate::default_vec::DefaultVec;
+use core::fmt::{Debug, Formatter};
+use core::marker::PhantomData;
+use core::mem;
+
+type Elt = u32;
+
+#[inline]
+fn split(x: usize) -> (usize, Elt, u32) {
+    let offset = (x % Elt::BITS as usize) as u32;
+    (x / Elt::BITS as usize, 1 << offset, offset)
+}
+
+/// A two-dimensional bit relation: a set of columns per row
+///
+/// Backed by a single [`DefaultVec<u32>`](DefaultVec), with row `r`'s bits for column `c` stored
+/// in word `r * words_per_row + c / 32`. This directly supports transitive-closure /
+/// reachability computations, where rows are usually worklist items and columns are the nodes
+/// reachable from them.
+///
+/// Growing a column past the current row stride re-lays out every existing row at the new,
+/// wider stride, so (like [`BitSet`](crate::BitSet)) it resizes its heap allocation whenever a
+/// column that wouldn't otherwise fit is added, and never shrinks it.
+pub struct BitMatrix<R = usize, C = usize> {
+    rows: DefaultVec<Elt>,
+    words_per_row: usize,
+    /// One past the highest row index ever passed to [`BitMatrix::insert`], [`BitMatrix::remove`]
+    /// or as the destination of [`BitMatrix::union_rows`]
+    ///
+    /// `rows.capacity() / words_per_row` can't be used for this instead, since `DefaultVec`'s
+    /// backing storage is free to over-allocate, so tracking it is required for
+    /// [`BitMatrix::ensure_words_per_row`] to know which rows to carry over during a relayout
+    num_rows: usize,
+    _marker: PhantomData<(R, C)>,
+}
+
+impl<R, C> Default for BitMatrix<R, C> {
+    fn default() -> Self {
+        BitMatrix {
+            rows: DefaultVec::default(),
+            words_per_row: 0,
+            num_rows: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<R, C> Clone for BitMatrix<R, C> {
+    fn clone(&self) -> Self {
+        BitMatrix {
+            rows: self.rows.clone(),
+            words_per_row: self.words_per_row,
+            num_rows: self.num_rows,
+            _marker: PhantomData,
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.rows.clone_from(&source.rows);
+        self.words_per_row = source.words_per_row;
+        self.num_rows = source.num_rows;
+    }
+}
+
+impl<R, C> PartialEq for BitMatrix<R, C> {
+    fn eq(&self, other: &Self) -> bool {
+        let rows = self.num_rows.max(other.num_rows);
+        let words = self.words_per_row.max(other.words_per_row);
+        (0..rows).all(|r| (0..words).all(|w| self.row_word(r, w) == other.row_word(r, w)))
+    }
+}
+
+impl<R, C> Eq for BitMatrix<R, C> {}
+
+impl<R, C> BitMatrix<R, C> {
+    /// Returns word `w` of row `r`, or `0` if it's past the current row stride
+    fn row_word(&self, r: usize, w: usize) -> Elt {
+        if w >= self.words_per_row {
+            0
+        } else {
+            self.rows.get(r * self.words_per_row + w)
+        }
+    }
+}
+
+impl<R: From<usize> + Into<usize> + Copy + Debug, C: From<usize> + Debug> Debug
+    for BitMatrix<R, C>
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_set()
+            .entries((0..self.num_rows).flat_map(|r| {
+                let r = R::from(r);
+                self.iter_row(r).map(move |c| (r, c))
+            }))
+            .finish()
+    }
+}
+
+impl<R: Into<usize>, C: Into<usize>> BitMatrix<R, C> {
+    /// Records that row `r` has been (or is about to be) written to, growing [`Self::num_rows`]
+    /// if needed
+    fn touch_row(&mut self, r: usize) {
+        self.num_rows = self.num_rows.max(r + 1);
+    }
+
+    /// Ensures every row has at least `words` words, re-laying out existing rows at the new
+    /// stride if a wider column was just inserted
+    fn ensure_words_per_row(&mut self, words: usize) {
+        if words <= self.words_per_row {
+            return;
+        }
+        let old_words_per_row = self.words_per_row;
+        let old_rows = mem::take(&mut self.rows);
+        self.words_per_row = words;
+        if old_words_per_row == 0 || self.num_rows == 0 {
+            return;
+        }
+        self.rows.reserve(self.num_rows * words - 1);
+        for row in 0..self.num_rows {
+            for w in 0..old_words_per_row {
+                let word = old_rows.get(row * old_words_per_row + w);
+                *self.rows.get_mut(row * words + w) = word;
+            }
+        }
+    }
+
+    /// Adds `(r, c)` to the relation
+    ///
+    /// Returns whether the relation did not already contain `(r, c)`
+    ///
+    /// ```rust
+    /// use default_vec2::BitMatrix;
+    /// let mut m: BitMatrix<usize, usize> = BitMatrix::default();
+    /// assert!(m.insert(0, 1));
+    /// assert!(!m.insert(0, 1));
+    /// assert!(m.contains(0, 1));
+    /// ```
+    ///
+    /// Inserting a wider column later on re-lays out every row touched so far, not just the ones
+    /// that happen to fit the old row stride:
+    /// ```rust
+    /// use default_vec2::BitMatrix;
+    /// let mut m: BitMatrix<usize, usize> = BitMatrix::default();
+    /// m.insert(1, 78);
+    /// m.insert(1, 136);
+    /// m.insert(5, 42);
+    /// m.insert(1, 264);
+    /// assert!(m.contains(5, 42));
+    /// ```
+    pub fn insert(&mut self, r: R, c: C) -> bool {
+        let r = r.into();
+        self.touch_row(r);
+        let (col_chunk, mask, _) = split(c.into());
+        self.ensure_words_per_row(col_chunk + 1);
+        let word = self.rows.get_mut(r * self.words_per_row + col_chunk);
+        let res = (*word & mask) == 0;
+        *word |= mask;
+        res
+    }
+
+    /// Removes `(r, c)` from the relation
+    ///
+    /// Returns whether the relation contained `(r, c)`
+    ///
+    /// ```rust
+    /// use default_vec2::BitMatrix;
+    /// let mut m: BitMatrix<usize, usize> = BitMatrix::default();
+    /// m.insert(0, 1);
+    /// assert!(m.remove(0, 1));
+    /// assert!(!m.remove(0, 1));
+    /// assert!(!m.contains(0, 1));
+    /// ```
+    pub fn remove(&mut self, r: R, c: C) -> bool {
+        let r = r.into();
+        self.touch_row(r);
+        let (col_chunk, mask, _) = split(c.into());
+        self.ensure_words_per_row(col_chunk + 1);
+        let word = self.rows.get_mut(r * self.words_per_row + col_chunk);
+        let res = (*word & mask) != 0;
+        *word &= !mask;
+        res
+    }
+
+    /// Checks whether the relation contains `(r, c)`
+    ///
+    /// ```rust
+    /// use default_vec2::BitMatrix;
+    /// let mut m: BitMatrix<usize, usize> = BitMatrix::default();
+    /// assert!(!m.contains(0, 1));
+    /// m.insert(0, 1);
+    /// assert!(m.contains(0, 1));
+    /// ```
+    pub fn contains(&self, r: R, c: C) -> bool {
+        if self.words_per_row == 0 {
+            return false;
+        }
+        let (col_chunk, mask, _) = split(c.into());
+        if col_chunk >= self.words_per_row {
+            return false;
+        }
+        let word = self.rows.get(r.into() * self.words_per_row + col_chunk);
+        (word & mask) != 0
+    }
+
+    /// ORs `src_row`'s words into `dst_row`
+    ///
+    /// Returns whether `dst_row` changed, so callers doing a reachability fixpoint know when to
+    /// keep `src_row` on the worklist
+    ///
+    /// ```rust
+    /// use default_vec2::BitMatrix;
+    /// let mut m: BitMatrix<usize, usize> = BitMatrix::default();
+    /// m.insert(0, 1);
+    /// m.insert(1, 2);
+    /// assert!(m.union_rows(0, 1));
+    /// assert!(!m.union_rows(0, 1));
+    /// assert_eq!(vec![1, 2], m.iter_row(1).collect::<Vec<_>>());
+    /// ```
+    pub fn union_rows(&mut self, src_row: R, dst_row: R) -> bool {
+        if self.words_per_row == 0 {
+            return false;
+        }
+        let src_row = src_row.into();
+        let dst_row = dst_row.into();
+        self.touch_row(dst_row);
+        let mut changed = false;
+        for w in 0..self.words_per_row {
+            let src_word = self.rows.get(src_row * self.words_per_row + w);
+            let dst_word = self.rows.get_mut(dst_row * self.words_per_row + w);
+            let new = *dst_word | src_word;
+            changed |= new != *dst_word;
+            *dst_word = new;
+        }
+        changed
+    }
+}
+
+impl<R: Into<usize>, C: From<usize>> BitMatrix<R, C> {
+    /// Iterates over the columns set in row `r`
+    ///
+    /// Run time is proportional to the number of words per row plus the number of columns set in
+    /// the row, since it skips a whole word at a time instead of testing every column
+    ///
+    /// ```rust
+    /// use default_vec2::BitMatrix;
+    /// let mut m: BitMatrix<usize, usize> = BitMatrix::default();
+    /// m.insert(0, 1);
+    /// m.insert(0, 42);
+    /// assert_eq!(vec![1, 42], m.iter_row(0).collect::<Vec<_>>());
+    /// ```
+    pub fn iter_row(&self, r: R) -> impl Iterator<Item = C> + '_ {
+        let r = r.into();
+        let words_per_row = self.words_per_row;
+        let rows = &self.rows;
+        WordBits::new((0..words_per_row).map(move |w| rows.get(r * words_per_row + w))).map(C::from)
+    }
+}
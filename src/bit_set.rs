@@ -2,21 +2,122 @@ use crate::default_vec::DefaultVec;
 use core::fmt::{Debug, Formatter};
 use core::iter;
 use core::marker::PhantomData;
-use core::ops::{BitAndAssign, BitOrAssign, BitXorAssign, SubAssign};
+use core::ops::{BitAndAssign, BitOrAssign, BitXorAssign, Bound, RangeBounds, SubAssign};
+use core::slice;
 
 type Elt = u32;
 
-/// A simple unbounded bitset that fits in 2 `usize`s worth of memory
+/// Number of `Elt` words that fit inline, without a heap allocation: two `usize`s worth of bits
+const INLINE_WORDS: usize = 2 * usize::BITS as usize / Elt::BITS as usize;
+
+/// Backing storage for a [`BitSet`]
 ///
-/// It resizes its heap allocation whenever a number that wouldn't otherwise fit in memory is added
-/// and doesn't ever shrink its memory so it could end of wasting memory if a very large element
-/// is added and then removed
+/// Stays inline (no heap allocation) while every element fits in the first `INLINE_WORDS` words,
+/// and transparently promotes to the heap `DefaultVec` form the first time [`Repr::get_mut`] or
+/// [`Repr::reserve`] is asked for an index that doesn't
 #[cfg_attr(feature = "serde-1", derive(serde::Serialize, serde::Deserialize))]
-pub struct BitSet<I = usize>(DefaultVec<Elt>, PhantomData<I>);
+#[derive(Clone)]
+enum Repr {
+    Inline([Elt; INLINE_WORDS]),
+    Heap(DefaultVec<Elt>),
+}
+
+impl Default for Repr {
+    fn default() -> Self {
+        Repr::Inline([0; INLINE_WORDS])
+    }
+}
+
+/// Builds the heap form of a set whose words used to be `inline`, with enough capacity for `i`
+fn promote(inline: &[Elt; INLINE_WORDS], i: usize) -> DefaultVec<Elt> {
+    let mut heap = DefaultVec::default();
+    heap.reserve(i);
+    for (idx, &word) in inline.iter().enumerate() {
+        *heap.get_mut(idx) = word;
+    }
+    heap
+}
+
+impl Repr {
+    fn capacity(&self) -> usize {
+        match self {
+            Repr::Inline(words) => words.len(),
+            Repr::Heap(v) => v.capacity(),
+        }
+    }
+
+    fn get(&self, i: usize) -> Elt {
+        match self {
+            Repr::Inline(words) => words.get(i).copied().unwrap_or_default(),
+            Repr::Heap(v) => v.get(i),
+        }
+    }
+
+    fn get_mut(&mut self, i: usize) -> &mut Elt {
+        if let Repr::Inline(words) = self {
+            if i >= words.len() {
+                *self = Repr::Heap(promote(words, i));
+            }
+        }
+        match self {
+            Repr::Inline(words) => &mut words[i],
+            Repr::Heap(v) => v.get_mut(i),
+        }
+    }
+
+    /// Ensures index `i` is addressable without returning a reference to it, promoting to the
+    /// heap form if `i` doesn't fit inline
+    fn reserve(&mut self, i: usize) {
+        match self {
+            Repr::Inline(words) => {
+                if i >= words.len() {
+                    *self = Repr::Heap(promote(words, i));
+                }
+            }
+            Repr::Heap(v) => {
+                if i >= v.capacity() {
+                    v.reserve(i)
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        match self {
+            Repr::Inline(words) => words.fill(0),
+            Repr::Heap(v) => v.clear(),
+        }
+    }
+
+    fn iter(&self) -> slice::Iter<'_, Elt> {
+        match self {
+            Repr::Inline(words) => words.iter(),
+            Repr::Heap(v) => v.iter(),
+        }
+    }
+
+    fn iter_mut(&mut self) -> slice::IterMut<'_, Elt> {
+        match self {
+            Repr::Inline(words) => words.iter_mut(),
+            Repr::Heap(v) => v.iter_mut(),
+        }
+    }
+}
+
+/// A simple unbounded bitset, backed by inline storage until it holds an element too large to
+/// fit there
+///
+/// Sets whose elements all fall within the first `2 * usize::BITS` bits live entirely inline,
+/// with no heap allocation at all; such a set is 3 `usize`s (the inline words plus the internal
+/// representation's discriminant). It resizes its heap allocation whenever a number that
+/// wouldn't otherwise fit in memory is added, and doesn't ever shrink its memory so it could end
+/// of wasting memory if a very large element is added and then removed
+#[cfg_attr(feature = "serde-1", derive(serde::Serialize, serde::Deserialize))]
+pub struct BitSet<I = usize>(Repr, PhantomData<I>);
 
 impl<I> Default for BitSet<I> {
     fn default() -> Self {
-        BitSet(DefaultVec::default(), PhantomData)
+        BitSet(Repr::default(), PhantomData)
     }
 }
 
@@ -32,7 +133,13 @@ impl<I> Clone for BitSet<I> {
 
 impl<I> PartialEq<Self> for BitSet<I> {
     fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0
+        let (shorter, longer) = if self.0.capacity() <= other.0.capacity() {
+            (&self.0, &other.0)
+        } else {
+            (&other.0, &self.0)
+        };
+        shorter.iter().eq(longer.iter().take(shorter.capacity()))
+            && longer.iter().skip(shorter.capacity()).all(|&word| word == 0)
     }
 }
 
@@ -44,6 +151,44 @@ fn split(x: usize) -> (usize, Elt, u32) {
     (x / Elt::BITS as usize, 1 << offset, offset)
 }
 
+/// Returns the inclusive start of `range`
+#[inline]
+fn range_start(range: &impl RangeBounds<usize>) -> usize {
+    match range.start_bound() {
+        Bound::Included(&s) => s,
+        Bound::Excluded(&s) => s + 1,
+        Bound::Unbounded => 0,
+    }
+}
+
+/// Normalizes `range` to an inclusive `[start, end]`, returning `None` if the range is empty
+///
+/// An unbounded end resolves to `capacity_bits - 1`, so a query over an unbounded range never
+/// looks past the bits the set has actually allocated
+#[inline]
+fn normalize_range(range: impl RangeBounds<usize>, capacity_bits: usize) -> Option<(usize, usize)> {
+    let start = range_start(&range);
+    let end = match range.end_bound() {
+        Bound::Included(&e) => e,
+        Bound::Excluded(&e) => e.checked_sub(1)?,
+        Bound::Unbounded => capacity_bits.checked_sub(1)?,
+    };
+    (start <= end).then_some((start, end))
+}
+
+/// Combines two word slices with `op`, padding whichever is shorter with zero words
+#[inline]
+fn zip_words<'a>(
+    a: slice::Iter<'a, Elt>,
+    b: slice::Iter<'a, Elt>,
+    op: impl Fn(Elt, Elt) -> Elt + 'a,
+) -> WordBits<impl Iterator<Item = Elt> + 'a> {
+    let len = a.len().max(b.len());
+    let mut a = a.copied().chain(iter::repeat(0));
+    let mut b = b.copied().chain(iter::repeat(0));
+    WordBits::new((0..len).map(move |_| op(a.next().unwrap(), b.next().unwrap())))
+}
+
 impl<I: Into<usize>> BitSet<I> {
     /// Adds an element to the set
     ///
@@ -124,15 +269,407 @@ impl<I: Into<usize>> BitSet<I> {
     pub fn clear(&mut self) {
         self.0.clear()
     }
+
+    /// Returns the number of elements in the set
+    ///
+    /// Run time is proportional to the number of words backing the set, not its largest element
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Returns `true` if the set contains no elements
+    ///
+    /// Run time is proportional to the number of words backing the set, not its largest element
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|&word| word == 0)
+    }
+
+    /// Inserts every element of `range` into the set
+    ///
+    /// Operates a full word at a time rather than calling [`BitSet::insert`] once per element
+    ///
+    /// An unbounded end stops at the last bit the set currently has allocated, or at `start` if
+    /// that is past it; no element beyond that is inserted
+    ///
+    /// ```rust
+    /// use default_vec2::BitSet;
+    /// let mut s: BitSet<usize> = BitSet::default();
+    /// s.insert_range(2..5);
+    /// assert_eq!(vec![2, 3, 4], s.iter().collect::<Vec<_>>());
+    ///
+    /// let mut s2: BitSet<usize> = BitSet::default();
+    /// s2.insert_range(200..);
+    /// assert!(s2.contains(200));
+    /// ```
+    pub fn insert_range(&mut self, range: impl RangeBounds<usize>) {
+        let capacity_bits = self.0.capacity() * Elt::BITS as usize;
+        let start = range_start(&range);
+        // An unbounded end normally resolves to `capacity_bits - 1`, but unlike a query, an
+        // insert must still grow to cover `start` even if that's past the current capacity
+        let end = match range.end_bound() {
+            Bound::Included(&e) => e,
+            Bound::Excluded(&e) => match e.checked_sub(1) {
+                Some(e) => e,
+                None => return,
+            },
+            Bound::Unbounded => capacity_bits.max(start + 1) - 1,
+        };
+        if start > end {
+            return;
+        }
+        let (start_chunk, _, start_off) = split(start);
+        let (end_chunk, _, end_off) = split(end);
+        if start_chunk == end_chunk {
+            let mask = (!0 >> (Elt::BITS - 1 - (end_off - start_off))) << start_off;
+            *self.0.get_mut(start_chunk) |= mask;
+            return;
+        }
+        self.0.reserve(end_chunk);
+        *self.0.get_mut(start_chunk) |= !0 << start_off;
+        for chunk in start_chunk + 1..end_chunk {
+            *self.0.get_mut(chunk) = !0;
+        }
+        *self.0.get_mut(end_chunk) |= !0 >> (Elt::BITS - 1 - end_off);
+    }
+
+    /// Removes every element of `range` from the set
+    ///
+    /// Operates a full word at a time rather than calling [`BitSet::remove`] once per element
+    ///
+    /// ```rust
+    /// use default_vec2::BitSet;
+    /// let mut s: BitSet<usize> = BitSet::from_iter([1, 2, 3, 4]);
+    /// s.remove_range(2..4);
+    /// assert_eq!(vec![1, 4], s.iter().collect::<Vec<_>>());
+    /// ```
+    pub fn remove_range(&mut self, range: impl RangeBounds<usize>) {
+        let capacity_bits = self.0.capacity() * Elt::BITS as usize;
+        let Some((start, end)) = normalize_range(range, capacity_bits) else {
+            return;
+        };
+        if start >= capacity_bits {
+            return;
+        }
+        let end = end.min(capacity_bits - 1);
+        let (start_chunk, _, start_off) = split(start);
+        let (end_chunk, _, end_off) = split(end);
+        if start_chunk == end_chunk {
+            let mask = (!0 >> (Elt::BITS - 1 - (end_off - start_off))) << start_off;
+            *self.0.get_mut(start_chunk) &= !mask;
+            return;
+        }
+        *self.0.get_mut(start_chunk) &= !(!0 << start_off);
+        for chunk in start_chunk + 1..end_chunk {
+            *self.0.get_mut(chunk) = 0;
+        }
+        *self.0.get_mut(end_chunk) &= !(!0 >> (Elt::BITS - 1 - end_off));
+    }
+
+    /// Checks whether every element of `range` is contained in the set
+    ///
+    /// Operates a full word at a time rather than calling [`BitSet::contains`] once per element
+    ///
+    /// With an unbounded end, only the bits the set currently has allocated are checked, and a
+    /// `start` past them gives `false`
+    ///
+    /// ```rust
+    /// use default_vec2::BitSet;
+    /// let s: BitSet<usize> = BitSet::from_iter([1, 2, 3, 4]);
+    /// assert!(s.contains_range(2..4));
+    /// assert!(!s.contains_range(2..10));
+    ///
+    /// let empty: BitSet<usize> = BitSet::default();
+    /// assert!(!empty.contains_range(200..));
+    /// ```
+    pub fn contains_range(&self, range: impl RangeBounds<usize>) -> bool {
+        let capacity_bits = self.0.capacity() * Elt::BITS as usize;
+        let start = range_start(&range);
+        // Unlike `normalize_range`'s generic empty-range handling, an unbounded end can never be
+        // vacuously satisfied: it's asking about a genuinely unbounded (non-empty) range, just
+        // one we only check up to the bits the set has allocated. If `start` itself isn't even
+        // allocated, the range can't be contained
+        let end = match range.end_bound() {
+            Bound::Included(&e) => e,
+            Bound::Excluded(&e) => match e.checked_sub(1) {
+                Some(e) => e,
+                None => return true,
+            },
+            Bound::Unbounded => {
+                if start >= capacity_bits {
+                    return false;
+                }
+                capacity_bits - 1
+            }
+        };
+        if start > end {
+            return true;
+        }
+        let (start_chunk, _, start_off) = split(start);
+        let (end_chunk, _, end_off) = split(end);
+        if start_chunk == end_chunk {
+            let mask = (!0 >> (Elt::BITS - 1 - (end_off - start_off))) << start_off;
+            return self.0.get(start_chunk) & mask == mask;
+        }
+        let start_mask = !0 << start_off;
+        if self.0.get(start_chunk) & start_mask != start_mask {
+            return false;
+        }
+        if (start_chunk + 1..end_chunk).any(|chunk| self.0.get(chunk) != !0) {
+            return false;
+        }
+        let end_mask = !0 >> (Elt::BITS - 1 - end_off);
+        self.0.get(end_chunk) & end_mask == end_mask
+    }
 }
 
 impl<I: From<usize> + Into<usize> + Copy> BitSet<I> {
     /// Iterate over all elements in the set
     ///
-    /// Run time is proportional to the largest element that has ever been in the set
+    /// Run time is proportional to the number of words backing the set plus the number of
+    /// elements in it, since it skips a whole word at a time instead of testing every bit
     pub fn iter(&self) -> impl Iterator<Item = I> + '_ {
-        let max = self.0.capacity() * (Elt::BITS as usize);
-        (0..max).map(I::from).filter(|x| self.contains(*x))
+        WordBits::new(self.0.iter().copied()).map(I::from)
+    }
+
+    /// Returns the smallest element in the set
+    ///
+    /// Run time is proportional to the number of words backing the set, not its largest element
+    pub fn first(&self) -> Option<I> {
+        self.0.iter().enumerate().find_map(|(idx, &word)| {
+            (word != 0).then(|| I::from(idx * Elt::BITS as usize + word.trailing_zeros() as usize))
+        })
+    }
+
+    /// Returns the largest element in the set
+    ///
+    /// Run time is proportional to the number of words backing the set, not its largest element
+    pub fn last(&self) -> Option<I> {
+        self.0.iter().enumerate().rev().find_map(|(idx, &word)| {
+            (word != 0).then(|| {
+                I::from(idx * Elt::BITS as usize + (Elt::BITS - 1 - word.leading_zeros()) as usize)
+            })
+        })
+    }
+
+    /// Borrowing iterator over the union of `self` and `other`, without mutating either
+    ///
+    /// ```rust
+    /// use default_vec2::BitSet;
+    /// let s1: BitSet<usize> = BitSet::from_iter([0, 1]);
+    /// let s2 = BitSet::from_iter([0, 42]);
+    /// assert_eq!(vec![0, 1, 42], s1.union(&s2).collect::<Vec<_>>());
+    /// ```
+    pub fn union<'a>(&'a self, other: &'a BitSet<I>) -> impl Iterator<Item = I> + 'a {
+        zip_words(self.0.iter(), other.0.iter(), |a, b| a | b).map(I::from)
+    }
+
+    /// Borrowing iterator over the intersection of `self` and `other`, without mutating either
+    ///
+    /// ```rust
+    /// use default_vec2::BitSet;
+    /// let s1: BitSet<usize> = BitSet::from_iter([0, 1]);
+    /// let s2 = BitSet::from_iter([0, 42]);
+    /// assert_eq!(vec![0], s1.intersection(&s2).collect::<Vec<_>>());
+    /// ```
+    pub fn intersection<'a>(&'a self, other: &'a BitSet<I>) -> impl Iterator<Item = I> + 'a {
+        zip_words(self.0.iter(), other.0.iter(), |a, b| a & b).map(I::from)
+    }
+
+    /// Borrowing iterator over the elements of `self` that aren't in `other`, without mutating
+    /// either
+    ///
+    /// ```rust
+    /// use default_vec2::BitSet;
+    /// let s1: BitSet<usize> = BitSet::from_iter([0, 1]);
+    /// let s2 = BitSet::from_iter([0, 42]);
+    /// assert_eq!(vec![1], s1.difference(&s2).collect::<Vec<_>>());
+    /// ```
+    pub fn difference<'a>(&'a self, other: &'a BitSet<I>) -> impl Iterator<Item = I> + 'a {
+        zip_words(self.0.iter(), other.0.iter(), |a, b| a & !b).map(I::from)
+    }
+
+    /// Borrowing iterator over the symmetric difference of `self` and `other`, without mutating
+    /// either
+    ///
+    /// ```rust
+    /// use default_vec2::BitSet;
+    /// let s1: BitSet<usize> = BitSet::from_iter([0, 1]);
+    /// let s2 = BitSet::from_iter([0, 42]);
+    /// assert_eq!(vec![1, 42], s1.symmetric_difference(&s2).collect::<Vec<_>>());
+    /// ```
+    pub fn symmetric_difference<'a>(&'a self, other: &'a BitSet<I>) -> impl Iterator<Item = I> + 'a {
+        zip_words(self.0.iter(), other.0.iter(), |a, b| a ^ b).map(I::from)
+    }
+}
+
+/// Scans a sequence of words for set bits, yielding each one's absolute bit index
+///
+/// Shared by [`BitSet::iter`] and [`BitMatrix::iter_row`](crate::BitMatrix::iter_row)
+pub(crate) struct WordBits<W> {
+    words: iter::Enumerate<W>,
+    word: Elt,
+    base: usize,
+}
+
+impl<W: Iterator<Item = Elt>> WordBits<W> {
+    pub(crate) fn new(words: W) -> Self {
+        WordBits {
+            words: words.enumerate(),
+            word: 0,
+            base: 0,
+        }
+    }
+}
+
+impl<W: Iterator<Item = Elt>> Iterator for WordBits<W> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.word == 0 {
+            let (idx, word) = self.words.next()?;
+            self.word = word;
+            self.base = idx * Elt::BITS as usize;
+        }
+        let tz = self.word.trailing_zeros();
+        self.word &= self.word - 1;
+        Some(self.base + tz as usize)
+    }
+}
+
+// `union_assign` was originally named `union`; it was renamed to the `*_assign` form here since
+// the borrowing combinator iterator above claims the `union` name instead, following
+// `BTreeSet`'s naming. `intersect`/`subtract` don't collide with `intersection`/`difference` and
+// keep their original names.
+impl<I> BitSet<I> {
+    /// Sets `self` to the union of `self` and `other`
+    ///
+    /// Returns `true` if `self` changed as a result, which callers doing fixpoint/dataflow
+    /// analysis can use to detect when a merge has stabilized
+    ///
+    /// ### Example:
+    /// ```
+    /// use default_vec2::BitSet;
+    /// let mut s1: BitSet<usize> = BitSet::from_iter([0, 1]);
+    /// let s2 = BitSet::from_iter([0, 42]);
+    /// assert!(s1.union_assign(&s2));
+    /// assert!(!s1.union_assign(&s2));
+    ///
+    /// assert_eq!(vec![0, 1, 42], s1.iter().collect::<Vec<_>>());
+    /// ```
+    pub fn union_assign(&mut self, other: &BitSet<I>) -> bool {
+        if other.0.capacity() > self.0.capacity() {
+            self.0.reserve(other.0.capacity())
+        }
+        let mut changed = false;
+        for (this, other) in self.0.iter_mut().zip(other.0.iter().copied()) {
+            let new = *this | other;
+            changed |= new != *this;
+            *this = new;
+        }
+        changed
+    }
+
+    /// Sets `self` to the intersection of `self` and `other`
+    ///
+    /// Returns `true` if `self` changed as a result, which callers doing fixpoint/dataflow
+    /// analysis can use to detect when a merge has stabilized
+    ///
+    /// ### Example:
+    /// ```
+    /// use default_vec2::BitSet;
+    /// let mut s1: BitSet<usize> = BitSet::from_iter([0, 1]);
+    /// let s2 = BitSet::from_iter([0, 42]);
+    /// assert!(s1.intersect(&s2));
+    /// assert!(!s1.intersect(&s2));
+    ///
+    /// assert_eq!(vec![0], s1.iter().collect::<Vec<_>>());
+    /// ```
+    pub fn intersect(&mut self, other: &BitSet<I>) -> bool {
+        let mut changed = false;
+        for (this, other) in self
+            .0
+            .iter_mut()
+            .zip(other.0.iter().copied().chain(iter::repeat(0)))
+        {
+            let new = *this & other;
+            changed |= new != *this;
+            *this = new;
+        }
+        changed
+    }
+
+    /// Sets `self` to the set difference of `self` and `other`
+    ///
+    /// Returns `true` if `self` changed as a result, which callers doing fixpoint/dataflow
+    /// analysis can use to detect when a merge has stabilized
+    ///
+    /// ### Example:
+    /// ```
+    /// use default_vec2::BitSet;
+    /// let mut s1: BitSet<usize> = BitSet::from_iter([0, 1]);
+    /// let s2 = BitSet::from_iter([0, 42]);
+    /// assert!(s1.subtract(&s2));
+    /// assert!(!s1.subtract(&s2));
+    ///
+    /// assert_eq!(vec![1], s1.iter().collect::<Vec<_>>());
+    /// ```
+    pub fn subtract(&mut self, other: &BitSet<I>) -> bool {
+        let mut changed = false;
+        for (this, other) in self.0.iter_mut().zip(other.0.iter().copied()) {
+            let new = *this & !other;
+            changed |= new != *this;
+            *this = new;
+        }
+        changed
+    }
+
+    /// Checks whether every element of `self` is also in `other`
+    ///
+    /// ### Example:
+    /// ```
+    /// use default_vec2::BitSet;
+    /// let s1: BitSet<usize> = BitSet::from_iter([0, 1]);
+    /// let s2 = BitSet::from_iter([0, 1, 42]);
+    /// assert!(s1.is_subset(&s2));
+    /// assert!(!s2.is_subset(&s1));
+    /// ```
+    pub fn is_subset(&self, other: &BitSet<I>) -> bool {
+        self.0
+            .iter()
+            .copied()
+            .zip(other.0.iter().copied().chain(iter::repeat(0)))
+            .all(|(word, other)| word & !other == 0)
+    }
+
+    /// Checks whether every element of `other` is also in `self`
+    ///
+    /// ### Example:
+    /// ```
+    /// use default_vec2::BitSet;
+    /// let s1: BitSet<usize> = BitSet::from_iter([0, 1, 42]);
+    /// let s2 = BitSet::from_iter([0, 1]);
+    /// assert!(s1.is_superset(&s2));
+    /// assert!(!s2.is_superset(&s1));
+    /// ```
+    pub fn is_superset(&self, other: &BitSet<I>) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Checks whether `self` and `other` have no elements in common
+    ///
+    /// ### Example:
+    /// ```
+    /// use default_vec2::BitSet;
+    /// let s1: BitSet<usize> = BitSet::from_iter([0, 1]);
+    /// let s2 = BitSet::from_iter([1, 42]);
+    /// assert!(!s1.is_disjoint(&s2));
+    /// assert!(s1.is_disjoint(&BitSet::from_iter([2, 42])));
+    /// ```
+    pub fn is_disjoint(&self, other: &BitSet<I>) -> bool {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .all(|(&word, &other)| word & other == 0)
     }
 }
 
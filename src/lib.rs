@@ -3,8 +3,10 @@
 
 extern crate alloc;
 
+mod bit_matrix;
 mod bit_set;
 mod default_vec;
 
+pub use bit_matrix::BitMatrix;
 pub use bit_set::BitSet;
 pub use default_vec::DefaultVec;